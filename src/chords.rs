@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+/// Common open-position and barre guitar chords, in chord3's `Vec<i8>`
+/// diagram format (leading base-fret, then one fret per string from low
+/// E to high e; `-1` for a muted string, `-2` for unknown/not drawn).
+/// For a barre chord (base-fret >= 2) the fret numbers are relative to
+/// the base, same as in a `{define}` directive; for an open chord
+/// (base-fret 0 or 1) they're the actual fret played.
+///
+/// These are the chords a song can use without an explicit `{define}`;
+/// anything else falls back to `voicing::synthesize_voicing`.
+pub fn get_known_chords() -> BTreeMap<String, Vec<i8>> {
+    let mut chords = BTreeMap::new();
+    let mut add = |name: &str, def: &[i8]| {
+        chords.insert(name.to_string(), def.to_vec());
+    };
+    add("C", &[1, -1, 3, 2, 0, 1, 0]);
+    add("C7", &[1, -1, 3, 2, 3, 1, 0]);
+    add("Cmaj7", &[1, -1, 3, 2, 0, 0, 0]);
+    add("Cm", &[3, -1, 1, 3, 3, 2, 1]);
+    add("D", &[1, -1, -1, 0, 2, 3, 2]);
+    add("D7", &[1, -1, -1, 0, 2, 1, 2]);
+    add("Dmaj7", &[1, -1, -1, 0, 2, 2, 2]);
+    add("Dm", &[1, -1, -1, 0, 2, 3, 1]);
+    add("E", &[1, 0, 2, 2, 1, 0, 0]);
+    add("E7", &[1, 0, 2, 0, 1, 0, 0]);
+    add("Emaj7", &[1, 0, 2, 1, 1, 0, 0]);
+    add("Em", &[1, 0, 2, 2, 0, 0, 0]);
+    add("F", &[1, 1, 3, 3, 2, 1, 1]);
+    add("F7", &[1, 1, 3, 1, 2, 1, 1]);
+    add("Fm", &[1, 1, 3, 3, 1, 1, 1]);
+    add("Fmaj7", &[1, -1, -1, 3, 2, 1, 0]);
+    add("G", &[1, 3, 2, 0, 0, 0, 3]);
+    add("G7", &[1, 3, 2, 0, 0, 0, 1]);
+    add("Gmaj7", &[1, 3, 2, 0, 0, 0, 2]);
+    add("Gm", &[3, 1, 3, 3, 1, 1, 1]);
+    add("A", &[1, -1, 0, 2, 2, 2, 0]);
+    add("A7", &[1, -1, 0, 2, 0, 2, 0]);
+    add("Amaj7", &[1, -1, 0, 2, 1, 2, 0]);
+    add("Am", &[1, -1, 0, 2, 2, 1, 0]);
+    add("B", &[2, -1, 1, 3, 3, 3, 1]);
+    add("B7", &[1, -1, 2, 1, 2, 0, 2]);
+    add("Bm", &[2, -1, 1, 3, 3, 2, 1]);
+    chords
+}
@@ -0,0 +1,155 @@
+use parser::BlockKind;
+
+/// A piece of song content, already transposed and ready to draw; the
+/// layout engine only needs to know how tall it is and what kind it is.
+#[derive(Clone)]
+pub enum Item {
+    Title(String),
+    SubTitle(String),
+    Comment(String),
+    Line(Vec<String>, BlockKind),
+    TabLine(String),
+}
+
+/// Page geometry: page size, margins, and how many columns to flow
+/// content into.
+pub struct Metrics {
+    pub page_width: f32,
+    pub page_height: f32,
+    pub margin: f32,
+    pub gutter: f32,
+    pub columns: usize,
+}
+
+impl Metrics {
+    pub fn column_width(&self) -> f32 {
+        let n = self.columns as f32;
+        (self.page_width - 2.0 * self.margin - self.gutter * (n - 1.0)) / n
+    }
+}
+
+/// An `Item` with its page and position resolved.
+pub struct Placed {
+    pub item: Item,
+    pub page: usize,
+    pub x: f32,
+    pub y: f32,
+}
+
+pub struct LayoutResult {
+    pub placed: Vec<Placed>,
+    pub pages: usize,
+}
+
+pub fn height_of(item: &Item) -> f32 {
+    match *item {
+        Item::Title(_) => 20.0,
+        Item::SubTitle(_) => 18.0,
+        Item::Comment(_) => 14.0,
+        Item::TabLine(_) => 12.0,
+        Item::Line(ref s, _) =>
+            1.2 * if s.len() > 1 { 14.0 + 10.0 } else { 14.0 },
+    }
+}
+
+/// Flow `items` across as many pages/columns as needed, keeping a
+/// title together with at least its first following line so a title
+/// never ends up alone at the bottom of a column.
+pub fn layout(items: &Vec<Item>, m: &Metrics) -> LayoutResult {
+    let col_width = m.column_width();
+    let top = m.page_height - 30.0;
+    let mut placed = vec!();
+    let mut page = 0;
+    let mut column = 0;
+    let mut y = top;
+    for (i, item) in items.iter().enumerate() {
+        let h = height_of(item);
+        let required = match *item {
+            Item::Title(_) =>
+                h + items.get(i + 1).map_or(0.0, |next| height_of(next)),
+            _ => h,
+        };
+        if y - required < m.margin {
+            column += 1;
+            if column >= m.columns {
+                column = 0;
+                page += 1;
+            }
+            y = top;
+        }
+        let x = m.margin + column as f32 * (col_width + m.gutter);
+        y = y - h;
+        placed.push(Placed { item: item.clone(), page: page, x: x, y: y });
+    }
+    LayoutResult { placed: placed, pages: page + 1 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::BlockKind;
+
+    fn metrics(page_height: f32, margin: f32, columns: usize) -> Metrics {
+        Metrics {
+            page_width: 100.0,
+            page_height: page_height,
+            margin: margin,
+            gutter: 0.0,
+            columns: columns,
+        }
+    }
+
+    #[test]
+    fn height_of_line_depends_on_whether_it_has_chords() {
+        assert_eq!(height_of(&Item::Comment("x".to_string())), 14.0);
+        assert_eq!(height_of(&Item::Line(vec!("lyrics".to_string()),
+                                         BlockKind::Normal)),
+                   1.2 * 14.0);
+        assert_eq!(height_of(&Item::Line(vec!("lyrics".to_string(),
+                                              "C".to_string()),
+                                         BlockKind::Normal)),
+                   1.2 * 24.0);
+    }
+
+    #[test]
+    fn column_width_splits_the_page_minus_margins_and_gutter() {
+        let m = Metrics {
+            page_width: 100.0, page_height: 100.0,
+            margin: 10.0, gutter: 0.0, columns: 2,
+        };
+        assert_eq!(m.column_width(), 40.0);
+    }
+
+    #[test]
+    fn layout_keeps_items_on_one_page_when_they_fit() {
+        let items = vec!(Item::Comment("a".to_string()),
+                          Item::Comment("b".to_string()));
+        let result = layout(&items, &metrics(100.0, 10.0, 1));
+        assert_eq!(result.pages, 1);
+        assert!(result.placed.iter().all(|p| p.page == 0));
+    }
+
+    #[test]
+    fn layout_flows_overflow_onto_new_pages() {
+        let items = vec!(Item::Comment("a".to_string()),
+                          Item::Comment("b".to_string()),
+                          Item::Comment("c".to_string()));
+        let result = layout(&items, &metrics(60.0, 10.0, 1));
+        assert_eq!(result.pages, 3);
+        let pages: Vec<usize> = result.placed.iter().map(|p| p.page).collect();
+        assert_eq!(pages, vec!(0, 1, 2));
+    }
+
+    #[test]
+    fn layout_fills_columns_before_starting_a_new_page() {
+        let items = vec!(Item::Comment("a".to_string()),
+                          Item::Comment("b".to_string()),
+                          Item::Comment("c".to_string()));
+        let result = layout(&items, &metrics(60.0, 10.0, 2));
+        assert_eq!(result.pages, 2);
+        assert_eq!(result.placed[0].page, 0);
+        assert_eq!(result.placed[1].page, 0);
+        assert!(result.placed[1].x > result.placed[0].x);
+        assert_eq!(result.placed[2].page, 1);
+    }
+}
@@ -0,0 +1,220 @@
+extern crate regex;
+
+use regex::Regex;
+use std::io;
+use std::io::BufRead;
+use std::fs::File;
+use std::sync::Mutex;
+
+/// The environment a `Line` appears in, set by a `{start_of_...}` /
+/// `{end_of_...}` directive pair.
+///
+/// `{start_of_grid}`/`{sog}` is intentionally out of scope here: a grid
+/// is a fretted-rhythm notation, not a lyric/chord environment like the
+/// other blocks, and would need its own rendering rather than another
+/// `BlockKind`. It falls through to the "unknown expression" warning
+/// below like any other unhandled directive.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockKind {
+    Normal,
+    Chorus,
+    Verse,
+}
+
+pub enum ChordFileExpression {
+    Title{s: String},
+    SubTitle{s: String},
+    Comment{s: String},
+    Key{s: String},
+    ChordDef{name: String, def: Vec<i8>},
+    Line{s: Vec<String>, kind: BlockKind},
+    /// A raw line inside a `{start_of_tab}`/`{eot}` block: shown verbatim,
+    /// with no chord-bracket extraction (brackets are literal there).
+    TabLine{s: String},
+}
+
+pub struct ChoproParser<R: io::Read> {
+    source: Mutex<io::Lines<io::BufReader<R>>>,
+    block: BlockKind,
+    in_tab: bool,
+}
+
+impl ChoproParser<File> {
+    pub fn open(path: &str) -> io::Result<ChoproParser<File>> {
+        let f = try!(File::open(path));
+        Ok(ChoproParser::new(f))
+    }
+}
+impl<R: io::Read> ChoproParser<R> {
+    pub fn new(source: R) -> ChoproParser<R> {
+        let reader = io::BufReader::new(source);
+        ChoproParser {
+            source: Mutex::new(reader.lines()),
+            block: BlockKind::Normal,
+            in_tab: false,
+        }
+    }
+
+    // Internal: Return the next line that is not a comment
+    fn nextline(&mut self) -> Option<String> {
+        loop {
+            match self.source.lock().unwrap().next() {
+                Some(Ok(line)) => {
+                    let comment_re = Regex::new(r"^\s*#").unwrap();
+                    if !comment_re.is_match(&line) {
+                        return Some(line)
+                    }
+                },
+                Some(Err(e)) => {
+                    println!("Failed to read source: {}", e);
+                    return None
+                },
+                _ => {
+                    return None
+                }
+            }
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for ChoproParser<R> {
+    type Item = ChordFileExpression;
+
+    fn next(&mut self) -> Option<ChordFileExpression> {
+        if let Some(line) = self.nextline() {
+            let re = Regex::new(r"\{(?P<cmd>\w+)(?::?\s*(?P<arg>.*))?\}").unwrap();
+            if let Some(caps) = re.captures(&line) {
+                let arg = caps.name("arg").unwrap_or("").to_string();
+                match caps.name("cmd").unwrap() {
+                    "t" | "title" => Some(ChordFileExpression::Title{s: arg}),
+                    "st" | "subtitle" => Some(ChordFileExpression::SubTitle{s:arg}),
+                    "c" => Some(ChordFileExpression::Comment{s:arg}),
+                    "key" => Some(ChordFileExpression::Key{s:arg}),
+                    "start_of_chorus" | "soc" => {
+                        self.block = BlockKind::Chorus;
+                        if arg.is_empty() { self.next() }
+                        else { Some(ChordFileExpression::Comment{s: arg}) }
+                    },
+                    "end_of_chorus" | "eoc" => {
+                        self.block = BlockKind::Normal;
+                        self.next()
+                    },
+                    "start_of_verse" | "sov" => {
+                        self.block = BlockKind::Verse;
+                        if arg.is_empty() { self.next() }
+                        else { Some(ChordFileExpression::Comment{s: arg}) }
+                    },
+                    "end_of_verse" | "eov" => {
+                        self.block = BlockKind::Normal;
+                        self.next()
+                    },
+                    "start_of_tab" | "sot" => {
+                        self.in_tab = true;
+                        if arg.is_empty() { self.next() }
+                        else { Some(ChordFileExpression::Comment{s: arg}) }
+                    },
+                    "end_of_tab" | "eot" => {
+                        self.in_tab = false;
+                        self.next()
+                    },
+                    "define" => {
+                        let re = Regex::new(r"(?i)^([\S]+)\s+base-fret\s+([x0-5])\s+frets(?:\s+([x0-5]))(?:\s+([x0-5]))(?:\s+([x0-5]))(?:\s+([x0-5]))(?:\s+([x0-5]))(?:\s+([x0-5]))\s*$").unwrap();
+                        if let Some(caps) = re.captures(&arg) {
+                            let s = |n| {
+                                match caps.at(n as usize+2) {
+                                    Some("x") | Some("X") | None => -1,
+                                    Some(s) => s.parse::<i8>().unwrap(),
+                                }
+                            };
+                            Some(ChordFileExpression::ChordDef {
+                                name: caps.at(1).unwrap().to_string(),
+                                def: vec!(s(0),
+                                          s(1), s(2), s(3),
+                                          s(4), s(5), s(6))
+                            })
+                        } else {
+                            let whole = caps.at(0).unwrap();
+                            println!("Warning: Bad chord definition {}", whole);
+                            Some(ChordFileExpression::Comment{s:whole.to_string()})
+                        }
+                    },
+                    x => {
+                        println!("unknown expression {}", x);
+                        Some(ChordFileExpression::Comment{s:caps.at(0).unwrap().to_string()})
+                    }
+                }
+            } else if self.in_tab {
+                Some(ChordFileExpression::TabLine{s: line})
+            } else {
+                let mut s = vec!();
+                let re = Regex::new(r"([^\[]*)(?:\[([^\]]*)\])?").unwrap();
+                for caps in re.captures_iter(&line) {
+                    s.push(caps.at(1).unwrap().to_string());
+                    if let Some(chord) = caps.at(2) {
+                        s.push(chord.to_string());
+                    }
+                }
+                Some(ChordFileExpression::Line{s: s, kind: self.block})
+            }
+        } else if self.in_tab || self.block != BlockKind::Normal {
+            println!("Warning: unterminated block at end of file");
+            self.in_tab = false;
+            self.block = BlockKind::Normal;
+            None
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn parser(text: &str) -> ChoproParser<Cursor<Vec<u8>>> {
+        ChoproParser::new(Cursor::new(text.as_bytes().to_vec()))
+    }
+
+    fn line_kind(expr: &ChordFileExpression) -> Option<BlockKind> {
+        match *expr {
+            ChordFileExpression::Line{kind, ..} => Some(kind),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn lines_inside_a_chorus_block_are_tagged_chorus() {
+        let p = parser("{start_of_chorus}\nLa la\n{end_of_chorus}\nLa la\n");
+        let exprs: Vec<_> = p.collect();
+        assert_eq!(line_kind(&exprs[0]), Some(BlockKind::Chorus));
+        assert_eq!(line_kind(&exprs[1]), Some(BlockKind::Normal));
+    }
+
+    #[test]
+    fn lines_inside_a_verse_block_are_tagged_verse() {
+        let p = parser("{sov}\nLa la\n{eov}\n");
+        let exprs: Vec<_> = p.collect();
+        assert_eq!(exprs.len(), 1);
+        assert_eq!(line_kind(&exprs[0]), Some(BlockKind::Verse));
+    }
+
+    #[test]
+    fn tab_block_lines_are_verbatim_with_literal_brackets() {
+        let p = parser("{start_of_tab}\ne|--0--[x]--|\n{end_of_tab}\n");
+        let exprs: Vec<_> = p.collect();
+        match exprs[0] {
+            ChordFileExpression::TabLine{ref s} =>
+                assert_eq!(s.as_str(), "e|--0--[x]--|"),
+            _ => panic!("expected a TabLine"),
+        }
+    }
+
+    #[test]
+    fn unterminated_block_closes_gracefully_at_eof() {
+        let p = parser("{start_of_chorus}\nLa la\n");
+        let exprs: Vec<_> = p.collect();
+        assert_eq!(exprs.len(), 1);
+        assert_eq!(line_kind(&exprs[0]), Some(BlockKind::Chorus));
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+use std::collections::{BTreeMap, BTreeSet};
+
+use parser::BlockKind;
+use voicing::synthesize_voicing;
+use super::SongRenderer;
+
+/// Renders a song as a standalone, stylable HTML songsheet: chords as
+/// `<span class="chord">` inline just before the lyrics they apply to
+/// (placing them above the lyrics is left to the linked `chord3.css`),
+/// and the used-chord diagrams as a small inline SVG each.
+pub struct HtmlRenderer<W: Write> {
+    out: W,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+}
+
+/// Render a single chord diagram (chord3's `Vec<i8>` format: leading
+/// base-fret followed by six string positions, `-1` muted, `-2` unknown)
+/// as a small inline SVG.
+fn diagram_svg(name: &str, def: &Vec<i8>) -> String {
+    let dx = 16.0;
+    let dy = 20.0;
+    let left = 12.0;
+    let top = 16.0;
+    let right = left + 5.0 * dx;
+    let bottom = top + 4.0 * dy;
+    let mut svg = format!(
+        "<svg class=\"chord-diagram\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">\n\
+         <text x=\"{cx}\" y=\"10\" text-anchor=\"middle\" \
+         font-style=\"italic\" font-size=\"11\">{name}</text>\n",
+        w = right + left, h = bottom + 10.0,
+        cx = (left + right) / 2.0, name = escape(name));
+    let barre = def[0];
+    if barre >= 2 {
+        svg.push_str(&format!(
+            "<text x=\"{x}\" y=\"{y}\" text-anchor=\"end\" \
+             font-size=\"10\">{barre}</text>\n",
+            x = left - 4.0, y = top + dy, barre = barre));
+    }
+    for fret in 0..5 {
+        let y = top + fret as f32 * dy;
+        svg.push_str(&format!(
+            "<line x1=\"{left}\" y1=\"{y}\" x2=\"{right}\" y2=\"{y}\" \
+             stroke=\"black\"/>\n", left = left, right = right, y = y));
+    }
+    for string in 0..6 {
+        let x = left + string as f32 * dx;
+        svg.push_str(&format!(
+            "<line x1=\"{x}\" y1=\"{top}\" x2=\"{x}\" y2=\"{bottom}\" \
+             stroke=\"black\"/>\n", x = x, top = top, bottom = bottom));
+    }
+    for string in 0..6 {
+        let x = left + string as f32 * dx;
+        match def[string + 1] {
+            -2 => (), // unknown chord, nothing to draw
+            -1 => svg.push_str(&format!(
+                "<text x=\"{x}\" y=\"{y}\" text-anchor=\"middle\" \
+                 font-size=\"10\">x</text>\n",
+                x = x, y = top - 4.0)),
+            0 => svg.push_str(&format!(
+                "<circle cx=\"{x}\" cy=\"{y}\" r=\"3\" fill=\"none\" \
+                 stroke=\"black\"/>\n",
+                x = x, y = top - 6.0)),
+            fret => svg.push_str(&format!(
+                "<circle cx=\"{x}\" cy=\"{y}\" r=\"4\" fill=\"black\"/>\n",
+                x = x, y = top + (fret as f32 - 0.5) * dy)),
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+impl<W: Write> HtmlRenderer<W> {
+    pub fn new(mut out: W, title: &str) -> io::Result<HtmlRenderer<W>> {
+        try!(write!(out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n\
+             <link rel=\"stylesheet\" href=\"chord3.css\">\n\
+             </head>\n<body>\n<div class=\"song\">\n",
+            escape(title)));
+        Ok(HtmlRenderer { out: out })
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        write!(self.out, "</div>\n</body>\n</html>\n")
+    }
+}
+
+impl<W: Write> SongRenderer for HtmlRenderer<W> {
+    fn title(&mut self, s: &str) -> io::Result<()> {
+        write!(self.out, "<h1 class=\"title\">{}</h1>\n", escape(s))
+    }
+
+    fn subtitle(&mut self, s: &str) -> io::Result<()> {
+        write!(self.out, "<h2 class=\"subtitle\">{}</h2>\n", escape(s))
+    }
+
+    fn comment(&mut self, s: &str) -> io::Result<()> {
+        write!(self.out, "<p class=\"comment\">{}</p>\n", escape(s))
+    }
+
+    fn chord_def(&mut self, _name: &str, _def: &Vec<i8>) -> io::Result<()> {
+        // Diagrams for used chords are emitted together in finish_song.
+        Ok(())
+    }
+
+    fn line(&mut self, s: &Vec<String>, kind: BlockKind) -> io::Result<()> {
+        let class = match kind {
+            BlockKind::Chorus => "line chorus",
+            BlockKind::Verse => "line verse",
+            BlockKind::Normal => "line",
+        };
+        try!(write!(self.out, "<div class=\"{}\">", class));
+        for (i, part) in s.iter().enumerate() {
+            if i % 2 == 1 {
+                try!(write!(self.out,
+                            "<span class=\"chord\">{}</span>",
+                            escape(part)));
+            } else {
+                try!(write!(self.out,
+                            "<span class=\"lyrics\">{}</span>",
+                            escape(part)));
+            }
+        }
+        write!(self.out, "</div>\n")
+    }
+
+    fn tab_line(&mut self, s: &str) -> io::Result<()> {
+        write!(self.out, "<pre class=\"tab\">{}</pre>\n", escape(s))
+    }
+
+    fn finish_song(&mut self,
+                   used_chords: &BTreeSet<String>,
+                   local_chords: &BTreeMap<String, Vec<i8>>,
+                   known_chords: &BTreeMap<String, Vec<i8>>)
+                   -> io::Result<()> {
+        try!(write!(self.out, "<div class=\"diagrams\">\n"));
+        for chord in used_chords.iter() {
+            if let Some(def) = local_chords.get(chord) {
+                try!(write!(self.out, "{}", diagram_svg(chord, def)));
+            } else if let Some(def) = known_chords.get(chord) {
+                try!(write!(self.out, "{}", diagram_svg(chord, def)));
+            } else if let Some(synthesized) = synthesize_voicing(chord) {
+                try!(write!(self.out, "{}", diagram_svg(chord, &synthesized)));
+            } else {
+                println!("Warning: Unknown chord '{}'.", chord);
+                try!(write!(self.out, "{}",
+                            diagram_svg(chord, &vec!(0,-2,-2,-2,-2,-2,-2,-2))));
+            }
+        }
+        write!(self.out, "</div>\n")
+    }
+}
@@ -0,0 +1,86 @@
+pub mod pdf;
+pub mod html;
+
+use std::io;
+use std::collections::{BTreeMap, BTreeSet};
+
+use parser::{ChoproParser, ChordFileExpression, BlockKind};
+use transpose::{transpose_chord, transpose_diagram, Transpose};
+
+/// One backend able to turn a parsed chord file into some output format.
+///
+/// A `SongRenderer` is driven by [`render_song`] once per expression the
+/// parser produces (except `{key}`, which `render_song` consumes itself),
+/// and gets a final chance to draw a summary (the used chord diagrams)
+/// via `finish_song`.
+pub trait SongRenderer {
+    fn title(&mut self, s: &str) -> io::Result<()>;
+    fn subtitle(&mut self, s: &str) -> io::Result<()>;
+    fn comment(&mut self, s: &str) -> io::Result<()>;
+    fn chord_def(&mut self, name: &str, def: &Vec<i8>) -> io::Result<()>;
+    fn line(&mut self, parts: &Vec<String>, kind: BlockKind) -> io::Result<()>;
+    fn tab_line(&mut self, s: &str) -> io::Result<()>;
+    fn finish_song(&mut self,
+                   used_chords: &BTreeSet<String>,
+                   local_chords: &BTreeMap<String, Vec<i8>>,
+                   known_chords: &BTreeMap<String, Vec<i8>>)
+                   -> io::Result<()>;
+}
+
+/// Drive a `SongRenderer` from a `ChoproParser`, keeping track of the
+/// chord definitions and chord names a song uses along the way.
+///
+/// `transpose` shifts every inline chord and `{define}` diagram (see the
+/// `transpose` module); pass `Transpose::None` to render the song as
+/// written. Spelling of the transposed chords follows the most recent
+/// `{key}` directive seen in the file, defaulting to sharps.
+pub fn render_song<R, B>(renderer: &mut B, source: ChoproParser<R>,
+                         known_chords: &BTreeMap<String, Vec<i8>>,
+                         transpose: &Transpose)
+                         -> io::Result<()>
+    where R: io::Read, B: SongRenderer
+{
+    let mut local_chords: BTreeMap<String, Vec<i8>> = BTreeMap::new();
+    let mut used_chords: BTreeSet<String> = BTreeSet::new();
+    let mut key = String::new();
+    for token in source {
+        try!(match token {
+            ChordFileExpression::Title{s} => renderer.title(&s),
+            ChordFileExpression::SubTitle{s} => renderer.subtitle(&s),
+            ChordFileExpression::Comment{s} => renderer.comment(&s),
+            ChordFileExpression::Key{s} => {
+                key = s;
+                Ok(())
+            },
+            ChordFileExpression::ChordDef{name, def} => {
+                let semitones = transpose.resolve(&key);
+                let name = transpose_chord(&name, semitones, &key);
+                let def = transpose_diagram(&def, semitones as i8);
+                try!(renderer.chord_def(&name, &def));
+                local_chords.insert(name, def);
+                Ok(())
+            },
+            ChordFileExpression::Line{s, kind} => {
+                let semitones = transpose.resolve(&key);
+                let s: Vec<String> = s.iter().enumerate().map(|(i, part)| {
+                    if i % 2 == 1 {
+                        transpose_chord(part, semitones, &key)
+                    } else {
+                        part.clone()
+                    }
+                }).collect();
+                for (i, part) in s.iter().enumerate() {
+                    if i % 2 == 1 {
+                        used_chords.insert(part.to_string());
+                    }
+                }
+                renderer.line(&s, kind)
+            },
+            ChordFileExpression::TabLine{s} => renderer.tab_line(&s),
+        })
+    }
+    // Remove non-chords that are displayed like chords above the text.
+    used_chords.remove("%");
+    used_chords.remove("");
+    renderer.finish_song(&used_chords, &local_chords, known_chords)
+}
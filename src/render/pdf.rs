@@ -0,0 +1,268 @@
+extern crate pdf;
+
+use std::io;
+use std::fs::File;
+use std::collections::{BTreeMap, BTreeSet};
+
+use pdf::{Canvas, Pdf, FontSource};
+
+use layout::{Item, Metrics, layout, height_of};
+use parser::BlockKind;
+use voicing::synthesize_voicing;
+use super::SongRenderer;
+
+fn chordbox<'a>(c: &mut Canvas<'a, File>, left: f32, top: f32,
+                name: &str, strings: &Vec<i8>)
+                -> io::Result<()> {
+    let dx = 5.0;
+    let dy = 7.0;
+    let right = left + 5.0 * dx;
+    let bottom = top - 4.4 * dy;
+    try!(c.center_text(left + 2.0 * dx, top + dy,
+                       FontSource::Helvetica_Oblique, 12.0, name));
+    let barre = strings[0];
+    let up =
+        if barre < 2 {
+            try!(c.set_line_width(1.0));
+            try!(c.line(left-0.15, top+0.5, right+0.15, top+0.5));
+            try!(c.stroke());
+            0.0
+        } else {
+            try!(c.right_text(left - 0.4 * dx, top - 0.9 * dy,
+                              FontSource::Helvetica, dy, &format!("{}", barre)));
+            1.6
+        };
+    try!(c.set_line_width(0.3));
+    for b in 0..5 {
+        let y = top - b as f32 * dy;
+        try!(c.line(left, y, right, y));
+    }
+    for s in 0..6 {
+        let x = left + s as f32 * dx;
+        try!(c.line(x, top+up, x, bottom));
+    }
+    try!(c.stroke());
+    let radius = 1.4;
+    let above = top + 2.0 + radius;
+    for s in 0..6 {
+        let x = left + s as f32 * dx;
+        match strings[s+1] {
+            -2 => (), // No-op for unknown chord
+            -1 => {
+                let (l, r) = (x-radius, x+radius);
+                let (t, b) = (above-radius, above+radius);
+                try!(c.line(l, t, r, b));
+                try!(c.line(r, t, l, b));
+                try!(c.stroke());
+            }
+            0 => {
+                try!(c.circle(x, above, radius));
+                try!(c.stroke());
+            }
+            y => {
+                let y = top - (y as f32 - 0.5) * dy;
+                try!(c.circle(x, y, radius+0.4));
+                try!(c.fill());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn draw_item<'a>(c: &mut Canvas<'a, File>, x: f32, y: f32, item: &Item) -> io::Result<()> {
+    match *item {
+        Item::Title(ref s) => c.text(|t| {
+            let font = t.get_font(FontSource::Times_Bold);
+            try!(t.set_font(&font, 18.0));
+            try!(t.pos(x, y));
+            t.show(s)
+        }),
+        Item::SubTitle(ref s) => c.text(|t| {
+            let font = t.get_font(FontSource::Times_Italic);
+            try!(t.set_font(&font, 16.0));
+            try!(t.pos(x, y));
+            t.show(s)
+        }),
+        Item::Comment(ref s) => c.text(|t| {
+            let font = t.get_font(FontSource::Times_Italic);
+            try!(t.set_font(&font, 14.0));
+            try!(t.pos(x, y));
+            t.show(s)
+        }),
+        Item::TabLine(ref s) => c.text(|t| {
+            let font = t.get_font(FontSource::Courier);
+            try!(t.set_font(&font, 10.0));
+            try!(t.pos(x, y));
+            t.show(s)
+        }),
+        Item::Line(ref s, kind) => {
+            let indent = if kind == BlockKind::Chorus { 10.0 } else { 0.0 };
+            let bar_x = x - 4.0;
+            let y_before = y + height_of(item);
+            let x = x + indent;
+            try!(c.text(|t| {
+                let text_size = 14.0;
+                let chord_size = 10.0;
+                let times = t.get_font(FontSource::Times_Roman);
+                let chordfont = t.get_font(FontSource::Helvetica_Oblique);
+                try!(t.set_font(&times, text_size));
+                try!(t.pos(x, y));
+                let mut last_chord_width = 0.0;
+                for (i, part) in s.iter().enumerate() {
+                    if i % 2 == 1 {
+                        try!(t.gsave());
+                        try!(t.set_rise(text_size));
+                        try!(t.set_font(&chordfont, chord_size));
+                        let chord_width =
+                            chordfont.get_width_raw(&part) as i32;
+                        try!(t.show_j(&part, chord_width));
+                        last_chord_width =
+                            (chord_width + 400) as f32 * chord_size / 1000.0;
+                        try!(t.grestore());
+                    } else {
+                        let part = { if part.len() > 0 { part.to_string() }
+                                     else { " ".to_string() } };
+                        let text_width = times.get_width(text_size, &part);
+                        if last_chord_width > text_width && i+1 < s.len() {
+                            let extra = last_chord_width - text_width;
+                            let n_space = part.chars()
+                                .filter(|&c| {c == ' '})
+                                .count();
+                            if n_space > 0 {
+                                try!(t.set_word_spacing(
+                                    extra / n_space as f32));
+                            } else {
+                                try!(t.set_char_spacing(
+                                    extra / part.len() as f32));
+                            }
+                        }
+                        try!(t.show(&part));
+                        if last_chord_width > text_width {
+                            try!(t.set_char_spacing(0.0));
+                            try!(t.set_word_spacing(0.0));
+                        }
+                    }
+                }
+                Ok(())
+            }));
+            if kind == BlockKind::Chorus {
+                try!(c.set_line_width(1.0));
+                try!(c.line(bar_x, y_before, bar_x, y));
+                try!(c.stroke());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Renders a song onto one or more PDF pages using `pdf::Canvas`. Unlike
+/// the other `SongRenderer` methods, which just buffer content, the
+/// actual drawing happens in `finish_song`, once the whole song is known
+/// and can be flowed across pages and columns.
+pub struct PdfRenderer<'a, 'b: 'a> {
+    document: &'a mut Pdf<'b, File>,
+    metrics: Metrics,
+    items: Vec<Item>,
+}
+
+impl<'a, 'b> PdfRenderer<'a, 'b> {
+    pub fn new(document: &'a mut Pdf<'b, File>, columns: usize) -> PdfRenderer<'a, 'b> {
+        let metrics = Metrics {
+            page_width: 596.0,
+            page_height: 842.0,
+            margin: 50.0,
+            gutter: 20.0,
+            columns: if columns > 0 { columns } else { 1 },
+        };
+        PdfRenderer { document: document, metrics: metrics, items: vec!() }
+    }
+}
+
+impl<'a, 'b> SongRenderer for PdfRenderer<'a, 'b> {
+    fn title(&mut self, s: &str) -> io::Result<()> {
+        self.items.push(Item::Title(s.to_string()));
+        Ok(())
+    }
+
+    fn subtitle(&mut self, s: &str) -> io::Result<()> {
+        self.items.push(Item::SubTitle(s.to_string()));
+        Ok(())
+    }
+
+    fn comment(&mut self, s: &str) -> io::Result<()> {
+        self.items.push(Item::Comment(s.to_string()));
+        Ok(())
+    }
+
+    fn chord_def(&mut self, _name: &str, _def: &Vec<i8>) -> io::Result<()> {
+        // Nothing to draw here; diagrams are summarised in finish_song.
+        Ok(())
+    }
+
+    fn line(&mut self, s: &Vec<String>, kind: BlockKind) -> io::Result<()> {
+        self.items.push(Item::Line(s.clone(), kind));
+        Ok(())
+    }
+
+    fn tab_line(&mut self, s: &str) -> io::Result<()> {
+        self.items.push(Item::TabLine(s.to_string()));
+        Ok(())
+    }
+
+    fn finish_song(&mut self,
+                   used_chords: &BTreeSet<String>,
+                   local_chords: &BTreeMap<String, Vec<i8>>,
+                   known_chords: &BTreeMap<String, Vec<i8>>)
+                   -> io::Result<()> {
+        let result = layout(&self.items, &self.metrics);
+        let (width, height) = (self.metrics.page_width, self.metrics.page_height);
+        let margin = self.metrics.margin;
+        let box_width = 40.0;
+        let per_row = ((width - 2.0 * margin) / box_width) as usize;
+        let per_row = if per_row > 0 { per_row } else { 1 };
+        // The diagram grid grows up from the bottom margin; if the last
+        // page's content reaches down into it, the grid gets a page of
+        // its own.
+        let rows = (used_chords.len() + per_row - 1) / per_row;
+        let grid_top = margin + rows as f32 * 40.0;
+        let lowest = result.placed.iter()
+            .filter(|p| p.page + 1 == result.pages)
+            .fold(height, |lowest, p| lowest.min(p.y));
+        let pages = if rows > 0 && lowest < grid_top { result.pages + 1 }
+                    else { result.pages };
+        {
+            let document = &mut self.document;
+            for page in 0..pages {
+                try!(document.render_page(width, height, |c| {
+                    for placed in result.placed.iter().filter(|p| p.page == page) {
+                        try!(draw_item(c, placed.x, placed.y, &placed.item));
+                    }
+                    // Lay the used-chord diagrams out as a wrapping grid at
+                    // the end of the song, below the last page's content.
+                    if page + 1 == pages {
+                        let left = margin;
+                        let bottom = margin;
+                        for (i, chord) in used_chords.iter().enumerate() {
+                            let x = left + (i % per_row) as f32 * box_width;
+                            let y = bottom + ((i / per_row) as f32) * 40.0;
+                            if let Some(chorddef) = local_chords.get(chord) {
+                                try!(chordbox(c, x, y, chord, chorddef));
+                            } else if let Some(chorddef) = known_chords.get(chord) {
+                                try!(chordbox(c, x, y, chord, chorddef));
+                            } else if let Some(synthesized) = synthesize_voicing(chord) {
+                                try!(chordbox(c, x, y, chord, &synthesized));
+                            } else {
+                                println!("Warning: Unknown chord '{}'.", chord);
+                                try!(chordbox(c, x, y, chord,
+                                              &vec!(0,-2,-2,-2,-2,-2,-2,-2)));
+                            }
+                        }
+                    }
+                    Ok(())
+                }));
+            }
+        }
+        self.items.clear();
+        Ok(())
+    }
+}
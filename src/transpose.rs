@@ -0,0 +1,273 @@
+extern crate regex;
+
+use regex::Regex;
+
+/// A parsed chord symbol: root note, its suffix (quality/extensions as
+/// written, untouched), and an optional slash bass note.
+struct Chord {
+    root: Note,
+    suffix: String,
+    bass: Option<Note>,
+}
+
+/// A note as a bare pitch class (0 = C .. 11 = B). The accidental it was
+/// written with is not kept; `spell` picks sharps or flats afresh.
+#[derive(Clone, Copy)]
+struct Note {
+    pitch_class: i32,
+}
+
+const SHARP_NAMES: [&'static str; 12] =
+    ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const FLAT_NAMES: [&'static str; 12] =
+    ["C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B"];
+
+/// Keys that are conventionally spelled with flats. Anything else
+/// (including an unrecognised `{key}`) is spelled with sharps.
+const FLAT_KEYS: [&'static str; 14] =
+    ["F", "Bb", "Eb", "Ab", "Db", "Gb", "Cb",
+     "Dm", "Gm", "Cm", "Fm", "Bbm", "Ebm", "Abm"];
+
+fn letter_pitch_class(letter: char) -> Option<i32> {
+    match letter {
+        'C' => Some(0),
+        'D' => Some(2),
+        'E' => Some(4),
+        'F' => Some(5),
+        'G' => Some(7),
+        'A' => Some(9),
+        'B' => Some(11),
+        _ => None,
+    }
+}
+
+impl Note {
+    fn parse(s: &str) -> Option<(Note, usize)> {
+        let mut chars = s.chars();
+        let letter = match chars.next() {
+            Some(c) => c,
+            None => return None,
+        };
+        let base = match letter_pitch_class(letter.to_ascii_uppercase()) {
+            Some(pc) => pc,
+            None => return None,
+        };
+        let mut len = 1;
+        let mut pc = base;
+        match s[1..].chars().next() {
+            Some('#') => { pc += 1; len += 1; }
+            Some('b') => { pc -= 1; len += 1; }
+            _ => (),
+        }
+        Some((Note { pitch_class: ((pc % 12) + 12) % 12 }, len))
+    }
+
+    fn transposed(&self, semitones: i32) -> Note {
+        Note { pitch_class: ((self.pitch_class + semitones) % 12 + 12) % 12 }
+    }
+
+    fn spell(&self, use_flats: bool) -> String {
+        let names = if use_flats { &FLAT_NAMES } else { &SHARP_NAMES };
+        names[self.pitch_class as usize].to_string()
+    }
+}
+
+/// Does the given `{key: ...}` argument conventionally use flats?
+fn key_uses_flats(key: &str) -> bool {
+    FLAT_KEYS.iter().any(|k| *k == key.trim())
+}
+
+impl Chord {
+    fn parse(s: &str) -> Option<Chord> {
+        let (root, root_len) = match Note::parse(s) {
+            Some(r) => r,
+            None => return None,
+        };
+        let rest = &s[root_len..];
+        let (suffix, bass) = match rest.find('/') {
+            Some(idx) => {
+                let bass_str = &rest[idx+1..];
+                let bass = Note::parse(bass_str).map(|(n, _)| n);
+                (rest[..idx].to_string(), bass)
+            }
+            None => (rest.to_string(), None),
+        };
+        Some(Chord { root: root, suffix: suffix, bass: bass })
+    }
+
+    fn transposed(&self, semitones: i32) -> Chord {
+        Chord {
+            root: self.root.transposed(semitones),
+            suffix: self.suffix.clone(),
+            bass: self.bass.map(|b| b.transposed(semitones)),
+        }
+    }
+
+    fn spell(&self, use_flats: bool) -> String {
+        let mut s = self.root.spell(use_flats);
+        s.push_str(&self.suffix);
+        if let Some(bass) = self.bass {
+            s.push('/');
+            s.push_str(&bass.spell(use_flats));
+        }
+        s
+    }
+}
+
+/// Transpose an inline chord symbol, e.g. `[F#m7/C#]`, by `semitones`
+/// half-steps, spelling the result according to `key` (a `{key: ...}`
+/// argument, or `""` for the default of sharps). Tokens that aren't
+/// recognisable chords (`%`, empty strings, ...) are returned unchanged.
+pub fn transpose_chord(chord: &str, semitones: i32, key: &str) -> String {
+    if semitones == 0 {
+        return chord.to_string();
+    }
+    match Chord::parse(chord) {
+        Some(c) => c.transposed(semitones).spell(key_uses_flats(key)),
+        None => chord.to_string(),
+    }
+}
+
+/// Transpose a `{define}` diagram by `semitones`, shifting `base-fret`
+/// and every fretted string by the same amount. A string that would
+/// fall below fret 0 is muted (`-1`) instead of going negative.
+pub fn transpose_diagram(def: &Vec<i8>, semitones: i8) -> Vec<i8> {
+    if semitones == 0 {
+        return def.clone();
+    }
+    let mut out = Vec::with_capacity(def.len());
+    // chordbox draws base-fret 0 and 1 identically (nut line, no barre
+    // label), so normalize them to the same value before shifting --
+    // otherwise two diagrams that render identically would drift apart
+    // after an equal transposition.
+    let base = if def[0] == 0 { 1 } else { def[0] };
+    out.push((base + semitones).max(0));
+    for &fret in def[1..].iter() {
+        if fret < 0 {
+            out.push(fret);
+        } else {
+            let shifted = fret + semitones;
+            out.push(if shifted < 0 { -1 } else { shifted });
+        }
+    }
+    out
+}
+
+/// A `--transpose` CLI argument, either a signed semitone count or a
+/// target key to transpose into relative to the song's `{key}`.
+pub enum Transpose {
+    None,
+    Semitones(i32),
+    ToKey(String),
+}
+
+impl Transpose {
+    /// Parse a `--transpose` CLI argument: `+3`/`-2` for a semitone
+    /// count, anything else is taken as a target key name.
+    pub fn parse(arg: &str) -> Transpose {
+        let signed_re = Regex::new(r"^[+-]\d+$").unwrap();
+        if signed_re.is_match(arg) {
+            match arg.parse::<i32>() {
+                Ok(n) => Transpose::Semitones(n),
+                Err(_) => {
+                    println!("Warning: --transpose argument '{}' is out of \
+                              range, ignoring", arg);
+                    Transpose::None
+                }
+            }
+        } else {
+            Transpose::ToKey(arg.to_string())
+        }
+    }
+
+    /// Resolve to a semitone count, given the song's current source key
+    /// (from its most recent `{key}` directive, or `""` if none seen).
+    pub fn resolve(&self, source_key: &str) -> i32 {
+        match *self {
+            Transpose::None => 0,
+            Transpose::Semitones(n) => n,
+            Transpose::ToKey(ref target) => {
+                match (Note::parse(target), Note::parse(source_key)) {
+                    (Some((target, _)), Some((source, _))) => {
+                        let mut diff = target.pitch_class - source.pitch_class;
+                        if diff < 0 { diff += 12; }
+                        diff
+                    }
+                    (None, _) => {
+                        println!("Warning: --transpose target key '{}' not \
+                                  recognised, not transposing", target);
+                        0
+                    }
+                    (_, None) => {
+                        println!("Warning: song has no recognised {{key}}, \
+                                  can't transpose to '{}'", target);
+                        0
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{transpose_chord, transpose_diagram, Transpose};
+
+    #[test]
+    fn transpose_chord_sharps_by_default() {
+        assert_eq!(transpose_chord("F#m7/C#", 2, ""), "G#m7/D#");
+    }
+
+    #[test]
+    fn transpose_chord_respects_flat_key() {
+        assert_eq!(transpose_chord("C", 6, "Eb"), "Gb");
+        assert_eq!(transpose_chord("C", 6, ""), "F#");
+    }
+
+    #[test]
+    fn transpose_chord_no_op_and_unrecognised() {
+        assert_eq!(transpose_chord("C", 0, ""), "C");
+        assert_eq!(transpose_chord("%", 3, ""), "%");
+    }
+
+    #[test]
+    fn transpose_diagram_shifts_base_and_frets() {
+        let d = transpose_diagram(&vec!(1, -1, 3, 2, 0, 1, 0), 2);
+        assert_eq!(d, vec!(3, -1, 5, 4, 2, 3, 2));
+    }
+
+    #[test]
+    fn transpose_diagram_keeps_muted_strings_muted() {
+        let d = transpose_diagram(&vec!(1, -1, 0, 2, 2, 1, 0), -1);
+        assert_eq!(d, vec!(0, -1, -1, 1, 1, 0, -1));
+    }
+
+    #[test]
+    fn transpose_diagram_base_fret_0_and_1_match() {
+        let a = transpose_diagram(&vec!(0, 0, 2, 2, 1, 0, 0), 3);
+        let b = transpose_diagram(&vec!(1, 0, 2, 2, 1, 0, 0), 3);
+        assert_eq!(a[0], b[0]);
+    }
+
+    #[test]
+    fn parse_overflowing_semitones_falls_back_to_none() {
+        match Transpose::parse("+99999999999") {
+            Transpose::None => (),
+            _ => panic!("expected Transpose::None for an out-of-range argument"),
+        }
+    }
+
+    #[test]
+    fn parse_key_name_is_to_key() {
+        match Transpose::parse("D") {
+            Transpose::ToKey(ref k) => assert_eq!(k, "D"),
+            _ => panic!("expected Transpose::ToKey"),
+        }
+    }
+
+    #[test]
+    fn resolve_to_key_computes_the_right_distance() {
+        assert_eq!(Transpose::parse("D").resolve("C"), 2);
+        assert_eq!(Transpose::parse("C").resolve("D"), 10);
+    }
+}
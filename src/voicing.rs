@@ -0,0 +1,214 @@
+/// Standard-tuning open-string pitch classes, low E to high E.
+const OPEN_STRINGS: [i32; 6] = [4, 9, 2, 7, 11, 4];
+
+fn letter_pitch_class(letter: char) -> Option<i32> {
+    match letter.to_ascii_uppercase() {
+        'C' => Some(0),
+        'D' => Some(2),
+        'E' => Some(4),
+        'F' => Some(5),
+        'G' => Some(7),
+        'A' => Some(9),
+        'B' => Some(11),
+        _ => None,
+    }
+}
+
+/// Split a chord name into its root pitch class and quality suffix
+/// (`"m"`, `"7"`, `"maj7"`, ...). Ignores any slash bass, which doesn't
+/// affect the fretted voicing.
+fn parse_root(name: &str) -> Option<(i32, String)> {
+    let name = match name.find('/') {
+        Some(idx) => &name[..idx],
+        None => name,
+    };
+    let letter = match name.chars().next() {
+        Some(c) => c,
+        None => return None,
+    };
+    let mut pc = match letter_pitch_class(letter) {
+        Some(pc) => pc,
+        None => return None,
+    };
+    let rest = match name[1..].chars().next() {
+        Some('#') => { pc += 1; &name[2..] }
+        Some('b') => { pc -= 1; &name[2..] }
+        _ => &name[1..],
+    };
+    Some((((pc % 12) + 12) % 12, rest.to_string()))
+}
+
+/// The pitch classes (relative to the root) making up a chord of the
+/// given quality, e.g. `"m7"` -> root, minor third, fifth, minor
+/// seventh.
+fn quality_intervals(suffix: &str) -> Vec<i32> {
+    let suffix = suffix.to_lowercase();
+    if suffix.starts_with("dim") {
+        return vec!(0, 3, 6);
+    }
+    if suffix.starts_with("sus2") {
+        return vec!(0, 2, 7);
+    }
+    if suffix.starts_with("sus4") {
+        return vec!(0, 5, 7);
+    }
+    let minor = suffix.starts_with('m') && !suffix.starts_with("maj");
+    let mut intervals = if minor { vec!(0, 3, 7) } else { vec!(0, 4, 7) };
+    if suffix.contains("maj7") {
+        intervals.push(11);
+    } else if suffix.contains('7') {
+        intervals.push(10);
+    }
+    intervals
+}
+
+struct Candidate {
+    frets: [i8; 6],
+    score: i32,
+}
+
+fn score_candidate(frets: &[i8; 6], root: i32, third: Option<i32>, fifth: Option<i32>)
+                   -> Option<i32> {
+    let mut sounding = 0;
+    let mut has_root = false;
+    let mut has_third = false;
+    let mut has_fifth = false;
+    let mut open_count = 0;
+    let mut min_fret = 127;
+    let mut max_fret = 0;
+    for (i, &fret) in frets.iter().enumerate() {
+        if fret < 0 {
+            continue;
+        }
+        sounding += 1;
+        if fret == 0 {
+            open_count += 1;
+        } else {
+            if fret < min_fret { min_fret = fret; }
+            if fret > max_fret { max_fret = fret; }
+        }
+        let pc = ((OPEN_STRINGS[i] + fret as i32) % 12 + 12) % 12;
+        if pc == root { has_root = true; }
+        if Some(pc) == third { has_third = true; }
+        if Some(pc) == fifth { has_fifth = true; }
+    }
+    if sounding < 3 || !has_root {
+        return None;
+    }
+    if max_fret > 0 && max_fret - min_fret > 4 {
+        return None;
+    }
+    // Penalise a muted string sandwiched between two fretted/sounding
+    // strings, since that's awkward to actually play.
+    let first_sounding = frets.iter().position(|&f| f >= 0);
+    let last_sounding = frets.iter().rposition(|&f| f >= 0);
+    let mut sandwiched_mutes = 0;
+    if let (Some(first), Some(last)) = (first_sounding, last_sounding) {
+        for &fret in frets[first..last+1].iter() {
+            if fret < 0 { sandwiched_mutes += 1; }
+        }
+    }
+    let mut score = sounding * 10;
+    if has_third { score += 15; }
+    if has_fifth { score += 15; }
+    score += open_count * 3;
+    score -= max_fret as i32;
+    score -= sandwiched_mutes * 8;
+    Some(score)
+}
+
+fn search(chord: &[i32], root: i32, third: Option<i32>, fifth: Option<i32>)
+          -> Option<[i8; 6]> {
+    let mut best: Option<Candidate> = None;
+    let mut frets = [-1i8; 6];
+    search_string(0, &mut frets, chord, root, third, fifth, &mut best);
+    best.map(|c| c.frets)
+}
+
+fn search_string(i: usize, frets: &mut [i8; 6], chord: &[i32], root: i32,
+                 third: Option<i32>, fifth: Option<i32>,
+                 best: &mut Option<Candidate>) {
+    if i == frets.len() {
+        if let Some(score) = score_candidate(frets, root, third, fifth) {
+            let better = match *best {
+                Some(ref b) => score > b.score,
+                None => true,
+            };
+            if better {
+                *best = Some(Candidate { frets: *frets, score: score });
+            }
+        }
+        return;
+    }
+    for fret in -1..6 {
+        // A string is either muted or sounds a note of the chord.
+        if fret >= 0 && !chord.contains(&((OPEN_STRINGS[i] + fret as i32) % 12)) {
+            continue;
+        }
+        frets[i] = fret;
+        search_string(i + 1, frets, chord, root, third, fifth, best);
+    }
+}
+
+/// Synthesize a playable guitar voicing for a chord that has no
+/// `{define}` and isn't in `get_known_chords`, by expanding its name
+/// into a pitch-class set and searching open-position fingerings.
+/// Returns `None` if no valid voicing is found within the fret window,
+/// in which case callers should fall back to a blank diagram.
+pub fn synthesize_voicing(name: &str) -> Option<Vec<i8>> {
+    let (root, suffix) = match parse_root(name) {
+        Some(x) => x,
+        None => return None,
+    };
+    let intervals = quality_intervals(&suffix);
+    let pc = |interval: i32| ((root + interval) % 12 + 12) % 12;
+    let third = intervals.get(1).map(|&i| pc(i));
+    let fifth = intervals.get(2).map(|&i| pc(i));
+    let chord: Vec<i32> = intervals.iter().map(|&i| pc(i)).collect();
+    search(&chord, root, third, fifth).map(|frets| {
+        let mut def = Vec::with_capacity(7);
+        def.push(0i8);
+        def.extend_from_slice(&frets);
+        def
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_root_splits_letter_accidental_and_bass() {
+        assert_eq!(parse_root("C"), Some((0, "".to_string())));
+        assert_eq!(parse_root("F#m7"), Some((6, "m7".to_string())));
+        assert_eq!(parse_root("Bb/D"), Some((10, "".to_string())));
+        assert_eq!(parse_root("h"), None);
+    }
+
+    #[test]
+    fn quality_intervals_cover_common_chord_types() {
+        assert_eq!(quality_intervals(""), vec!(0, 4, 7));
+        assert_eq!(quality_intervals("m"), vec!(0, 3, 7));
+        assert_eq!(quality_intervals("7"), vec!(0, 4, 7, 10));
+        assert_eq!(quality_intervals("maj7"), vec!(0, 4, 7, 11));
+        assert_eq!(quality_intervals("dim"), vec!(0, 3, 6));
+    }
+
+    #[test]
+    fn synthesize_voicing_finds_a_playable_shape() {
+        let def = synthesize_voicing("Cmaj7").expect("should find a voicing");
+        assert_eq!(def[0], 0);
+        let sounding: Vec<i8> =
+            def[1..].iter().cloned().filter(|&f| f >= 0).collect();
+        assert!(sounding.len() >= 3);
+        let has_root = def[1..].iter().enumerate().any(|(i, &f)| {
+            f >= 0 && ((OPEN_STRINGS[i] + f as i32) % 12 + 12) % 12 == 0
+        });
+        assert!(has_root);
+    }
+
+    #[test]
+    fn synthesize_voicing_rejects_unparseable_names() {
+        assert_eq!(synthesize_voicing("not a chord"), None);
+    }
+}